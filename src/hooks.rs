@@ -0,0 +1,61 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const COMMIT_MSG_HOOK: &str = r#"#!/bin/sh
+# Installed by `git-cc init`.
+# Enforces the Conventional Commits format on commits made outside git-cc,
+# e.g. `git commit -m "..."` or commits made in an editor.
+#
+# Merge, squash and revert commits get their message from git itself, not
+# from a contributor, so they're exempt from the Conventional Commits format.
+if [ -e "$(git rev-parse --git-path MERGE_HEAD)" ] || [ -e "$(git rev-parse --git-path SQUASH_MSG)" ]; then
+    exit 0
+fi
+case "$(head -n 1 "$1")" in
+    "Revert \""*) exit 0 ;;
+esac
+
+exec git-cc validate-message "$1"
+"#;
+
+fn git_hooks_dir() -> io::Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "not inside a git repository",
+        ));
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
+
+fn write_hook(path: &Path, contents: &str) -> io::Result<()> {
+    fs::write(path, contents)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = fs::metadata(path)?.permissions();
+        permissions.set_mode(0o755);
+        fs::set_permissions(path, permissions)?;
+    }
+
+    Ok(())
+}
+
+pub fn install() -> io::Result<()> {
+    let hooks_dir = git_hooks_dir()?;
+    fs::create_dir_all(&hooks_dir)?;
+
+    let commit_msg_hook = hooks_dir.join("commit-msg");
+    write_hook(&commit_msg_hook, COMMIT_MSG_HOOK)?;
+    println!("Installed commit-msg hook at {}", commit_msg_hook.display());
+
+    Ok(())
+}
@@ -1,6 +1,35 @@
+use std::fs;
 use std::process::{self, Command};
 
+use clap::{Parser, Subcommand};
 use dialoguer::{theme::ColorfulTheme, Editor, FuzzySelect, Input};
+use serde::Deserialize;
+
+mod hooks;
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    commit_types: Option<Vec<CommitType>>,
+    scopes: Option<Vec<String>>,
+    max_subject_length: Option<usize>,
+}
+
+const DEFAULT_MAX_SUBJECT_LENGTH: usize = 72;
+
+fn load_config() -> Option<Config> {
+    for path in [".git-cc.toml", "git-cc.toml"] {
+        if let Ok(contents) = fs::read_to_string(path) {
+            match toml::from_str(&contents) {
+                Ok(config) => return Some(config),
+                Err(err) => {
+                    eprintln!("Failed to parse {}: {}", path, err);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+    None
+}
 
 #[derive(Debug)]
 struct Message {
@@ -9,25 +38,110 @@ struct Message {
     description: String,
     body: String,
     breaking_change: String,
+    footers: Vec<(String, String)>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ValidationError {
+    EmptyDescription,
+    DescriptionEndsWithPeriod,
+    MultilineDescription,
+    SubjectTooLong { length: usize, max: usize },
+    UnknownCommitType(String),
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ValidationError::EmptyDescription => write!(f, "description must not be empty"),
+            ValidationError::DescriptionEndsWithPeriod => {
+                write!(f, "description must not end with a period")
+            }
+            ValidationError::MultilineDescription => {
+                write!(f, "description must not contain newlines")
+            }
+            ValidationError::SubjectTooLong { length, max } => write!(
+                f,
+                "subject line is {} characters long, exceeds the maximum of {}",
+                length, max
+            ),
+            ValidationError::UnknownCommitType(commit_type) => {
+                write!(f, "\"{}\" is not a known commit type", commit_type)
+            }
+        }
+    }
 }
 
 impl Message {
-    fn format(self) -> String {
-        let mut message = String::new();
-        message.push_str(&self.commit_type);
+    fn subject(&self) -> String {
+        let mut subject = self.commit_type.clone();
         if self.scope.len() != 0 {
-            message.push_str(format!("({})", self.scope).as_str());
+            subject.push_str(format!("({})", self.scope).as_str());
+        }
+        subject.push_str(": ");
+        subject.push_str(&self.description);
+        subject
+    }
+
+    fn validate(
+        &self,
+        known_commit_types: &[String],
+        max_subject_length: usize,
+    ) -> Result<(), ValidationError> {
+        if self.description.len() == 0 {
+            return Err(ValidationError::EmptyDescription);
+        }
+        if self.description.ends_with('.') {
+            return Err(ValidationError::DescriptionEndsWithPeriod);
         }
-        message.push_str(": ");
-        message.push_str(&self.description);
+        if self.description.contains('\n') {
+            return Err(ValidationError::MultilineDescription);
+        }
+        if !known_commit_types.iter().any(|t| t == &self.commit_type) {
+            return Err(ValidationError::UnknownCommitType(
+                self.commit_type.clone(),
+            ));
+        }
+        let subject = self.subject();
+        let subject_length = subject.chars().count();
+        if subject_length > max_subject_length {
+            return Err(ValidationError::SubjectTooLong {
+                length: subject_length,
+                max: max_subject_length,
+            });
+        }
+        Ok(())
+    }
+
+    fn format(self) -> String {
+        let mut message = self.subject();
         if self.body.len() != 0 {
             message.push_str("\n\n");
             message.push_str(&self.body);
         }
+
+        let mut footer_lines: Vec<String> = self
+            .footers
+            .iter()
+            .map(|(token, value)| {
+                if value.starts_with('#') {
+                    format!("{} {}", token, value)
+                } else {
+                    format!("{}: {}", token, value)
+                }
+            })
+            .collect();
         if self.breaking_change.len() != 0 {
+            footer_lines.push(format!("BREAKING CHANGE: {}", self.breaking_change));
+        }
+        // Footers form a single contiguous trailer block (one blank line
+        // before it, one newline between entries) so tools like
+        // `git interpret-trailers` still recognize every footer.
+        if !footer_lines.is_empty() {
             message.push_str("\n\n");
-            message.push_str(format!("BREAKING CHANGE: {}", self.breaking_change).as_str());
+            message.push_str(&footer_lines.join("\n"));
         }
+
         message
     }
 }
@@ -44,6 +158,7 @@ mod tests {
             description: String::from("add api"),
             body: String::from(""),
             breaking_change: String::from(""),
+            footers: vec![],
         };
         assert_eq!(message.format(), "feat: add api")
     }
@@ -56,6 +171,7 @@ mod tests {
             description: String::from("add api"),
             body: String::from(""),
             breaking_change: String::from(""),
+            footers: vec![],
         };
         assert_eq!(message.format(), "feat(cli): add api")
     }
@@ -68,6 +184,7 @@ mod tests {
             description: String::from("add api"),
             body: String::from("This is a body"),
             breaking_change: String::from(""),
+            footers: vec![],
         };
         assert_eq!(
             message.format(),
@@ -85,6 +202,7 @@ This is a body"#
             description: String::from("add api"),
             body: String::from("This is a body"),
             breaking_change: String::from(""),
+            footers: vec![],
         };
         assert_eq!(
             message.format(),
@@ -102,6 +220,7 @@ This is a body"#
             description: String::from("add api"),
             body: String::from("This is a body"),
             breaking_change: String::from("remove api"),
+            footers: vec![],
         };
         assert_eq!(
             message.format(),
@@ -112,41 +231,249 @@ This is a body
 BREAKING CHANGE: remove api"#
         )
     }
+
+    #[test]
+    fn format_with_footers() {
+        let message = Message {
+            commit_type: String::from("feat"),
+            scope: String::from(""),
+            description: String::from("add api"),
+            body: String::from("This is a body"),
+            breaking_change: String::from(""),
+            footers: vec![
+                (String::from("Closes"), String::from("#123")),
+                (String::from("Reviewed-by"), String::from("Alice")),
+            ],
+        };
+        assert_eq!(
+            message.format(),
+            r#"feat: add api
+
+This is a body
+
+Closes #123
+Reviewed-by: Alice"#
+        )
+    }
+
+    #[test]
+    fn format_with_footers_and_breaking_change() {
+        let message = Message {
+            commit_type: String::from("feat"),
+            scope: String::from(""),
+            description: String::from("add api"),
+            body: String::from(""),
+            breaking_change: String::from("remove api"),
+            footers: vec![(String::from("Closes"), String::from("#123"))],
+        };
+        assert_eq!(
+            message.format(),
+            r#"feat: add api
+
+Closes #123
+BREAKING CHANGE: remove api"#
+        )
+    }
+
+    fn valid_message() -> Message {
+        Message {
+            commit_type: String::from("feat"),
+            scope: String::from("cli"),
+            description: String::from("add api"),
+            body: String::from(""),
+            breaking_change: String::from(""),
+            footers: vec![],
+        }
+    }
+
+    #[test]
+    fn validate_accepts_valid_message() {
+        let known_commit_types = vec![String::from("feat")];
+        assert_eq!(valid_message().validate(&known_commit_types, 72), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_empty_description() {
+        let known_commit_types = vec![String::from("feat")];
+        let mut message = valid_message();
+        message.description = String::from("");
+        assert_eq!(
+            message.validate(&known_commit_types, 72),
+            Err(ValidationError::EmptyDescription)
+        );
+    }
+
+    #[test]
+    fn validate_rejects_trailing_period_in_description() {
+        let known_commit_types = vec![String::from("feat")];
+        let mut message = valid_message();
+        message.description = String::from("add api.");
+        assert_eq!(
+            message.validate(&known_commit_types, 72),
+            Err(ValidationError::DescriptionEndsWithPeriod)
+        );
+    }
+
+    #[test]
+    fn validate_rejects_multiline_description() {
+        let known_commit_types = vec![String::from("feat")];
+        let mut message = valid_message();
+        message.description = String::from("add\napi");
+        assert_eq!(
+            message.validate(&known_commit_types, 72),
+            Err(ValidationError::MultilineDescription)
+        );
+    }
+
+    #[test]
+    fn validate_rejects_unknown_commit_type() {
+        let known_commit_types = vec![String::from("fix")];
+        assert_eq!(
+            valid_message().validate(&known_commit_types, 72),
+            Err(ValidationError::UnknownCommitType(String::from("feat")))
+        );
+    }
+
+    #[test]
+    fn validate_rejects_subject_exceeding_max_length() {
+        let known_commit_types = vec![String::from("feat")];
+        assert_eq!(
+            valid_message().validate(&known_commit_types, 5),
+            Err(ValidationError::SubjectTooLong { length: 18, max: 5 })
+        );
+    }
+
+    #[test]
+    fn validate_counts_subject_length_in_characters_not_bytes() {
+        let known_commit_types = vec![String::from("feat")];
+        let mut message = valid_message();
+        // "feat(cli): café" is 15 characters but 16 bytes (é is 2 bytes in UTF-8).
+        message.description = String::from("café");
+        assert_eq!(message.validate(&known_commit_types, 15), Ok(()));
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitType {
+    text: String,
+    description: String,
 }
 
-fn select_commit_type() -> String {
+fn default_commit_types() -> Vec<CommitType> {
+    [
+        ("feat", "A new feature"),
+        ("fix", "A bug fix"),
+        (
+            "build",
+            "Changes that affect the build system or external dependencies",
+        ),
+        ("chore", "Other changes that don't modify src or test files"),
+        ("ci", "Changes to our CI configuration files and scripts"),
+        ("docs", "Documentation only changes"),
+        ("perf", "A code change that improves performance"),
+        (
+            "refactor",
+            "A code change that neither fixes a bug nor adds a feature",
+        ),
+        ("revert", "Reverts a previous commit"),
+        ("style", "Changes that do not affect the meaning of the code"),
+        ("test", "Adding missing tests or correcting existing tests"),
+    ]
+    .iter()
+    .map(|(text, description)| CommitType {
+        text: text.to_string(),
+        description: description.to_string(),
+    })
+    .collect()
+}
+
+fn resolve_commit_types(config: &Option<Config>) -> Vec<CommitType> {
+    config
+        .as_ref()
+        .and_then(|c| c.commit_types.as_ref())
+        .map_or_else(default_commit_types, |types| {
+            types
+                .iter()
+                .map(|c| CommitType {
+                    text: c.text.clone(),
+                    description: c.description.clone(),
+                })
+                .collect()
+        })
+}
+
+#[derive(Debug, Default)]
+struct Overrides {
+    commit_type: Option<String>,
+    scope: Option<String>,
+    description: Option<String>,
+    body: Option<String>,
+    breaking_change: Option<String>,
+    footers: Option<Vec<(String, String)>>,
+}
+
+fn select_commit_type(config: &Option<Config>, overrides: &Overrides) -> String {
+    if let Some(commit_type) = &overrides.commit_type {
+        return commit_type.clone();
+    }
+
     // conventional commit type
-    let selections = vec![
-        "feat", "fix", "build", "chore", "ci", "docs", "perf", "refactor", "revert", "style",
-        "test",
-    ];
+    let selections = resolve_commit_types(config);
+
+    let items: Vec<String> = selections
+        .iter()
+        .map(|c| format!("{:<9}: {}", c.text, c.description))
+        .collect();
 
     let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
         .with_prompt("Select the type of change that you're committing:")
         .default(0)
-        .items(&selections[..])
+        .items(&items[..])
         .interact()
         .unwrap();
 
-    selections[selection].to_string()
+    selections[selection].text.clone()
 }
 
-fn write_scope() -> String {
-    Input::with_theme(&ColorfulTheme::default())
-        .with_prompt("Write the commit scope (optional):")
-        .allow_empty(true)
-        .interact_text()
-        .unwrap()
+fn write_scope(config: &Option<Config>, overrides: &Overrides) -> String {
+    if let Some(scope) = &overrides.scope {
+        return scope.clone();
+    }
+
+    match config.as_ref().and_then(|c| c.scopes.as_ref()) {
+        Some(scopes) => {
+            let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt("Select the commit scope:")
+                .default(0)
+                .items(&scopes[..])
+                .interact()
+                .unwrap();
+            scopes[selection].clone()
+        }
+        None => Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("Write the commit scope (optional):")
+            .allow_empty(true)
+            .interact_text()
+            .unwrap(),
+    }
 }
 
-fn write_description() -> String {
+fn write_description(overrides: &Overrides) -> String {
+    if let Some(description) = &overrides.description {
+        return description.clone();
+    }
+
     Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Write a short description:")
         .interact_text()
         .unwrap()
 }
 
-fn write_body() -> String {
+fn write_body(overrides: &Overrides) -> String {
+    if let Some(body) = &overrides.body {
+        return body.clone();
+    }
+
     Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Write a detail description (optional):")
         .allow_empty(true)
@@ -154,7 +481,11 @@ fn write_body() -> String {
         .unwrap()
 }
 
-fn write_breaking_change() -> String {
+fn write_breaking_change(overrides: &Overrides) -> String {
+    if let Some(breaking_change) = &overrides.breaking_change {
+        return breaking_change.clone();
+    }
+
     Input::with_theme(&ColorfulTheme::default())
         .with_prompt("Write a breaking change (optional):")
         .allow_empty(true)
@@ -162,18 +493,50 @@ fn write_breaking_change() -> String {
         .unwrap()
 }
 
-fn create_message() -> Message {
-    let commit_type = select_commit_type();
-    let scope = write_scope();
-    let description = write_description();
-    let body = write_body();
-    let breaking_change = write_breaking_change();
+fn parse_footer(footer: &str) -> Option<(String, String)> {
+    footer
+        .split_once(": ")
+        .or_else(|| footer.split_once(' '))
+        .map(|(token, value)| (token.to_string(), value.to_string()))
+}
+
+fn write_footers(overrides: &Overrides) -> Vec<(String, String)> {
+    if let Some(footers) = &overrides.footers {
+        return footers.clone();
+    }
+
+    let mut footers = Vec::new();
+    loop {
+        let footer: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("Add a footer, e.g. \"Closes #123\" or \"Reviewed-by: Alice\" (optional):")
+            .allow_empty(true)
+            .interact_text()
+            .unwrap();
+        if footer.len() == 0 {
+            break;
+        }
+        match parse_footer(&footer) {
+            Some(footer) => footers.push(footer),
+            None => eprintln!("Could not parse footer \"{}\", skipping", footer),
+        }
+    }
+    footers
+}
+
+fn create_message(config: &Option<Config>, overrides: &Overrides) -> Message {
+    let commit_type = select_commit_type(config, overrides);
+    let scope = write_scope(config, overrides);
+    let description = write_description(overrides);
+    let body = write_body(overrides);
+    let footers = write_footers(overrides);
+    let breaking_change = write_breaking_change(overrides);
     let message = Message {
         commit_type,
         scope,
         description,
         body,
         breaking_change,
+        footers,
     };
     message
 }
@@ -187,10 +550,204 @@ fn commit(message: &str) {
         .expect("commit failed");
 }
 
+fn resolve_editor() -> String {
+    if let Ok(output) = Command::new("git").args(["config", "core.editor"]).output() {
+        if output.status.success() {
+            let editor = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if editor.len() != 0 {
+                return editor;
+            }
+        }
+    }
+
+    for var in ["GIT_EDITOR", "VISUAL", "EDITOR"] {
+        if let Ok(editor) = std::env::var(var) {
+            if editor.len() != 0 {
+                return editor;
+            }
+        }
+    }
+
+    String::from("vi")
+}
+
+fn parse_commit_message(raw: &str) -> Option<Message> {
+    let subject = raw
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+
+    let (head, description) = subject.split_once(": ")?;
+    let (commit_type, scope) = match head.find('(') {
+        Some(idx) if head.ends_with(')') => (
+            head[..idx].to_string(),
+            head[idx + 1..head.len() - 1].to_string(),
+        ),
+        _ => (head.to_string(), String::new()),
+    };
+
+    Some(Message {
+        commit_type,
+        scope,
+        description: description.to_string(),
+        body: String::new(),
+        breaking_change: String::new(),
+        footers: vec![],
+    })
+}
+
+fn validate_message_file(path: &std::path::Path) {
+    let config = load_config();
+    let known_commit_types: Vec<String> = resolve_commit_types(&config)
+        .iter()
+        .map(|c| c.text.clone())
+        .collect();
+    let max_subject_length = config
+        .as_ref()
+        .and_then(|c| c.max_subject_length)
+        .unwrap_or(DEFAULT_MAX_SUBJECT_LENGTH);
+
+    let raw = fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("Could not read {}: {}", path.display(), err);
+        process::exit(1);
+    });
+
+    let message = parse_commit_message(&raw).unwrap_or_else(|| {
+        eprintln!("Could not parse commit message, expected \"type(scope): description\"");
+        process::exit(1);
+    });
+
+    if let Err(err) = message.validate(&known_commit_types, max_subject_length) {
+        eprintln!("Invalid commit message: {}", err);
+        process::exit(1);
+    }
+}
+
+#[derive(Parser)]
+#[command(name = "git-cc", about = "Interactively build Conventional Commits messages")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Print the formatted commit message instead of running `git commit`
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Prefill the commit type and skip the type prompt
+    #[arg(long = "type")]
+    commit_type: Option<String>,
+
+    /// Prefill the commit scope and skip the scope prompt
+    #[arg(long)]
+    scope: Option<String>,
+
+    /// Prefill the description and skip the description prompt
+    #[arg(long)]
+    description: Option<String>,
+
+    /// Prefill the body and skip the body prompt
+    #[arg(long)]
+    body: Option<String>,
+
+    /// Prefill the breaking change and skip the breaking change prompt
+    #[arg(long)]
+    breaking: Option<String>,
+
+    /// Add a footer, e.g. "Closes: #123" (may be repeated). Skips the
+    /// footer prompt; so does --dry-run, where it defaults to no footers.
+    #[arg(long = "footer")]
+    footers: Vec<String>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Install a commit-msg hook that enforces the Conventional Commits format
+    Init,
+    /// Validate a commit message file (used internally by the installed hook)
+    #[command(hide = true)]
+    ValidateMessage { path: std::path::PathBuf },
+}
+
 pub fn run() {
-    let message = create_message();
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Commands::Init) => {
+            if let Err(err) = hooks::install() {
+                eprintln!("Failed to install git hook: {}", err);
+                process::exit(1);
+            }
+        }
+        Some(Commands::ValidateMessage { path }) => validate_message_file(&path),
+        None => run_interactive(
+            Overrides {
+                commit_type: cli.commit_type,
+                scope: cli.scope,
+                description: cli.description,
+                body: cli.body,
+                breaking_change: cli.breaking,
+                // A closed stdin in --dry-run/CI use can't answer the
+                // footer prompt either, so bypass it there too.
+                footers: if cli.dry_run || !cli.footers.is_empty() {
+                    Some(cli.footers.iter().filter_map(|f| parse_footer(f)).collect())
+                } else {
+                    None
+                },
+            },
+            cli.dry_run,
+        ),
+    }
+}
+
+fn run_interactive(overrides: Overrides, dry_run: bool) {
+    let config = load_config();
+    let known_commit_types: Vec<String> = resolve_commit_types(&config)
+        .iter()
+        .map(|c| c.text.clone())
+        .collect();
+    let max_subject_length = config
+        .as_ref()
+        .and_then(|c| c.max_subject_length)
+        .unwrap_or(DEFAULT_MAX_SUBJECT_LENGTH);
+
+    let message = loop {
+        let message = create_message(&config, &overrides);
+        match message.validate(&known_commit_types, max_subject_length) {
+            Ok(()) => break message,
+            Err(err) => {
+                eprintln!("Invalid commit message: {}", err);
+                // If every field the error could be fixed by came from a
+                // flag, there is no prompt left to re-enter it in, and
+                // looping would just hit the same error forever.
+                let unresolvable = match &err {
+                    ValidationError::EmptyDescription
+                    | ValidationError::DescriptionEndsWithPeriod
+                    | ValidationError::MultilineDescription => overrides.description.is_some(),
+                    ValidationError::UnknownCommitType(_) => overrides.commit_type.is_some(),
+                    // A prompted scope can still fix the error, unless the
+                    // subject is already too long without any scope.
+                    ValidationError::SubjectTooLong { .. } => {
+                        overrides.commit_type.is_some()
+                            && overrides.description.is_some()
+                            && (overrides.scope.is_some()
+                                || message.commit_type.chars().count()
+                                    + 2
+                                    + message.description.chars().count()
+                                    > max_subject_length)
+                    }
+                };
+                if unresolvable {
+                    process::exit(1);
+                }
+            }
+        }
+    };
     let formatted_message = message.format();
 
+    if dry_run {
+        println!("{}", formatted_message);
+        return;
+    }
+
     let selections = vec!["Commit with it", "Continue with editor", "Cancel"];
     let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
         .with_prompt(format!("Are you OK this message?\n{}", formatted_message))
@@ -205,7 +762,15 @@ pub fn run() {
         }
         // Continue with editor
         1 => {
-            if let Some(rv) = Editor::new().edit(&formatted_message).unwrap() {
+            let editor = resolve_editor();
+            let edited = Editor::new()
+                .executable(&editor)
+                .edit(&formatted_message)
+                .unwrap_or_else(|err| {
+                    eprintln!("Could not launch editor \"{}\": {}", editor, err);
+                    process::exit(1);
+                });
+            if let Some(rv) = edited {
                 commit(&rv);
             } else {
                 process::exit(1);